@@ -0,0 +1,220 @@
+//! Pluggable text-injection backends. The daemon typically runs as root (for
+//! /dev/input access) but has to type into the *user's* graphical session, so every
+//! backend goes through `run_as_user`, which carries the SUDO_USER-forwarding logic
+//! that used to live inline in `run_wtype`.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// Types text and emits backspaces into the focused window of a graphical session.
+pub trait Injector {
+    fn backspaces(&self, n: usize);
+    fn type_text(&self, text: &str);
+    /// Move the cursor left by `n` characters, for the `$|$` cursor marker.
+    fn cursor_left(&self, n: usize);
+}
+
+/// Environment variables needed for a command to reach the invoking user's session
+/// when the daemon itself runs as root (the common case, for /dev/input access).
+fn user_session_env() -> Vec<(String, String)> {
+    let mut env_vars = Vec::new();
+    let real_uid = env::var("SUDO_UID").unwrap_or_default();
+
+    if let Ok(xdg) = env::var("XDG_RUNTIME_DIR") {
+        env_vars.push(("XDG_RUNTIME_DIR".into(), xdg));
+    } else if !real_uid.is_empty() {
+        env_vars.push(("XDG_RUNTIME_DIR".into(), format!("/run/user/{}", real_uid)));
+    }
+
+    if let Ok(wayland) = env::var("WAYLAND_DISPLAY") {
+        env_vars.push(("WAYLAND_DISPLAY".into(), wayland));
+    }
+    if let Ok(display) = env::var("DISPLAY") {
+        env_vars.push(("DISPLAY".into(), display));
+    }
+
+    if let Ok(user) = env::var("SUDO_USER") {
+        env_vars.push(("USER".into(), user));
+    }
+    env_vars
+}
+
+/// Build `cmd` with `args`, wired to run in the invoking user's session via
+/// `sudo -u` when running as root, or directly otherwise.
+fn command_for_user(cmd: &str, args: &[&str]) -> Command {
+    if let Ok(sudo_user) = env::var("SUDO_USER") {
+        let mut command = Command::new("sudo");
+        command.arg("-u").arg(&sudo_user).arg("env");
+        for (k, v) in user_session_env() {
+            command.arg(format!("{}={}", k, v));
+        }
+        command.arg(cmd).args(args);
+        command
+    } else {
+        let mut command = Command::new(cmd);
+        command.args(args);
+        command
+    }
+}
+
+/// Run `cmd` with `args`, forwarded into the invoking user's session via `sudo -u`
+/// when running as root, or run directly otherwise.
+fn run_as_user(cmd: &str, args: &[&str]) {
+    let _ = command_for_user(cmd, args).status();
+}
+
+/// Like `run_as_user`, but captures stdout instead of firing-and-forgetting --
+/// for probes (e.g. the focused window's app class) rather than injection.
+pub(crate) fn run_as_user_output(cmd: &str, args: &[&str]) -> String {
+    command_for_user(cmd, args)
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_default()
+}
+
+/// `which`-style PATH lookup, so detection doesn't shell out just to check availability.
+fn is_on_path(cmd: &str) -> bool {
+    let Ok(path) = env::var("PATH") else { return false };
+    env::split_paths(&path).any(|dir| Path::new(&dir).join(cmd).is_file())
+}
+
+/// Wayland injector backed by `wtype`.
+pub struct Wtype;
+
+impl Injector for Wtype {
+    fn backspaces(&self, n: usize) {
+        if n == 0 { return }
+        let mut args = Vec::new();
+        for _ in 0..n {
+            args.push("-k".to_string());
+            args.push("BackSpace".to_string());
+        }
+        let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_as_user("wtype", &refs);
+    }
+
+    fn type_text(&self, text: &str) {
+        run_as_user("wtype", &["--", text]);
+    }
+
+    fn cursor_left(&self, n: usize) {
+        if n == 0 { return }
+        let mut args = Vec::new();
+        for _ in 0..n {
+            args.push("-k".to_string());
+            args.push("Left".to_string());
+        }
+        let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_as_user("wtype", &refs);
+    }
+}
+
+/// uinput-backed injector via `ydotool`. Works on both Wayland and X11.
+pub struct Ydotool;
+
+impl Injector for Ydotool {
+    fn backspaces(&self, n: usize) {
+        if n == 0 { return }
+        // evdev keycode 14 is Backspace; "14:1 14:0" is one press+release pair.
+        let mut args = vec!["key".to_string()];
+        for _ in 0..n {
+            args.push("14:1".to_string());
+            args.push("14:0".to_string());
+        }
+        let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_as_user("ydotool", &refs);
+    }
+
+    fn type_text(&self, text: &str) {
+        run_as_user("ydotool", &["type", "--", text]);
+    }
+
+    fn cursor_left(&self, n: usize) {
+        if n == 0 { return }
+        // evdev keycode 105 is Left.
+        let mut args = vec!["key".to_string()];
+        for _ in 0..n {
+            args.push("105:1".to_string());
+            args.push("105:0".to_string());
+        }
+        let refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_as_user("ydotool", &refs);
+    }
+}
+
+/// X11 injector backed by `xdotool`.
+pub struct Xdotool;
+
+impl Injector for Xdotool {
+    fn backspaces(&self, n: usize) {
+        if n == 0 { return }
+        run_as_user("xdotool", &["key", "--repeat", &n.to_string(), "BackSpace"]);
+    }
+
+    fn type_text(&self, text: &str) {
+        run_as_user("xdotool", &["type", "--", text]);
+    }
+
+    fn cursor_left(&self, n: usize) {
+        if n == 0 { return }
+        run_as_user("xdotool", &["key", "--repeat", &n.to_string(), "Left"]);
+    }
+}
+
+/// Probe `$WAYLAND_DISPLAY`/`$DISPLAY` and `$PATH` to pick the first available
+/// backend: `wtype` on Wayland, falling back to `ydotool` (works on both), then
+/// `xdotool` on X11.
+pub fn detect_backend() -> Option<Box<dyn Injector>> {
+    let wayland = env::var("WAYLAND_DISPLAY").is_ok();
+    let x11 = env::var("DISPLAY").is_ok();
+
+    if wayland && is_on_path("wtype") {
+        return Some(Box::new(Wtype));
+    }
+    if is_on_path("ydotool") {
+        return Some(Box::new(Ydotool));
+    }
+    if x11 && is_on_path("xdotool") {
+        return Some(Box::new(Xdotool));
+    }
+    None
+}
+
+/// Build the named backend regardless of what's detected on PATH, for `--backend`.
+pub fn backend_by_name(name: Backend) -> Box<dyn Injector> {
+    match name {
+        Backend::Wtype => Box::new(Wtype),
+        Backend::Ydotool => Box::new(Ydotool),
+        Backend::Xdotool => Box::new(Xdotool),
+    }
+}
+
+#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+pub enum Backend {
+    Wtype,
+    Ydotool,
+    Xdotool,
+}
+
+/// Read the system clipboard, honoring whichever display server is actually in
+/// use -- `wl-paste` under Wayland, `xclip`/`xsel` under X11 -- rather than
+/// assuming Wayland. Shared by the `clipboard` var and the Lua `clipboard()`
+/// host function so neither locks an X11 user out of clipboard access.
+pub(crate) fn read_clipboard() -> String {
+    let wayland = env::var("WAYLAND_DISPLAY").is_ok();
+    let x11 = env::var("DISPLAY").is_ok();
+
+    if wayland && is_on_path("wl-paste") {
+        return run_as_user_output("wl-paste", &["-n"]);
+    }
+    if x11 && is_on_path("xclip") {
+        return run_as_user_output("xclip", &["-selection", "clipboard", "-o"]);
+    }
+    if x11 && is_on_path("xsel") {
+        return run_as_user_output("xsel", &["--clipboard", "--output"]);
+    }
+    run_as_user_output("wl-paste", &["-n"])
+}
@@ -1,4 +1,13 @@
+mod injector;
+mod reload;
+mod script;
+mod winclass;
+
+use clap::Parser;
 use evdev::{Device, EventType, Key};
+use injector::Backend;
+use regex::Regex;
+use reload::ReloadWatcher;
 use serde::Deserialize;
 use std::{
     collections::HashMap,
@@ -8,9 +17,38 @@ use std::{
     path::PathBuf,
     process,
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 
+/// Lightweight espanso replacement for Wayland and X11.
+#[derive(Debug, Parser)]
+#[command(name = "text_expander", about = "lightweight espanso replacement for Wayland and X11")]
+struct Cli {
+    /// Run detached from the terminal as a background daemon.
+    #[arg(short, long)]
+    daemon: bool,
+
+    /// Override the config search root (default: ~/.config/text_expander).
+    #[arg(long, value_name = "DIR")]
+    config: Option<PathBuf>,
+
+    /// Pin a specific input device instead of using the find_keyboards() heuristic.
+    #[arg(long, value_name = "PATH")]
+    device: Option<PathBuf>,
+
+    /// Print every candidate keyboard under /dev/input and exit.
+    #[arg(long)]
+    list_devices: bool,
+
+    /// Load configs, print the resolved trigger table, and exit without polling.
+    #[arg(long)]
+    dump: bool,
+
+    /// Force a specific injection backend instead of auto-detecting one.
+    #[arg(long, value_enum)]
+    backend: Option<Backend>,
+}
+
 // Espanso-compatible config format
 #[derive(Debug, Deserialize)]
 struct EspansoConfig {
@@ -28,6 +66,42 @@ struct Match {
     replace: Option<String>,
     #[serde(default)]
     vars: Vec<Var>,
+    /// Regex pattern, matched against the end of the buffer (implicitly anchored
+    /// with a trailing `$`). Captures are exposed as `{{1}}`/`{{name}}` vars.
+    #[serde(default)]
+    regex: Option<String>,
+    /// Require a non-word character (or buffer start) immediately before the match.
+    #[serde(default)]
+    word: bool,
+    #[serde(flatten)]
+    scope: Scope,
+}
+
+/// Limits where a match is allowed to fire, by the focused window's app class.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Scope {
+    #[serde(default)]
+    filter: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+}
+
+impl Scope {
+    /// Whether this scope actually restricts which app classes can fire --
+    /// i.e. whether knowing the focused window's class could change the outcome.
+    fn is_scoped(&self) -> bool {
+        !self.filter.is_empty() || !self.exclude.is_empty()
+    }
+
+    /// An empty `filter` means "all apps"; `exclude` always wins over `filter`.
+    fn allows(&self, app_class: Option<&str>) -> bool {
+        if let Some(class) = app_class {
+            if self.exclude.iter().any(|c| c == class) {
+                return false;
+            }
+        }
+        self.filter.is_empty() || app_class.map_or(false, |class| self.filter.iter().any(|c| c == class))
+    }
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -44,17 +118,46 @@ struct VarParams {
     format: Option<String>,
     cmd: Option<String>,
     echo: Option<String>,
+    source: Option<String>,
 }
 
 #[derive(Clone)]
 struct Trigger {
     replace: String,
     vars: Vec<Var>,
+    scope: Scope,
+    word: bool,
+}
+
+/// espanso's `$|$` cursor marker: strip it from the expanded text and report how
+/// many characters follow it, so the caller can emit that many left-arrow presses.
+fn extract_cursor_marker(text: String) -> (String, usize) {
+    const MARKER: &str = "$|$";
+    match text.find(MARKER) {
+        Some(idx) => {
+            let after = text[idx + MARKER.len()..].chars().count();
+            let mut stripped = text;
+            stripped.replace_range(idx..idx + MARKER.len(), "");
+            (stripped, after)
+        }
+        None => (text, 0),
+    }
 }
 
 impl Trigger {
-    fn expand(&self) -> String {
+    fn expand(&self) -> (String, usize) {
+        self.expand_with(&HashMap::new())
+    }
+
+    /// Like `expand()`, but seeded with `captures` (regex capture groups) so they're
+    /// substituted directly and are visible to `script` vars via their `vars` table.
+    fn expand_with(&self, captures: &HashMap<String, String>) -> (String, usize) {
         let mut result = self.replace.clone();
+        let mut resolved: HashMap<String, String> = captures.clone();
+
+        for (name, value) in captures {
+            result = result.replace(&format!("{{{{{}}}}}", name), value);
+        }
 
         for var in &self.vars {
             let value = match var.var_type.as_str() {
@@ -69,20 +172,59 @@ impl Trigger {
                         String::new()
                     }
                 }
-                "clipboard" => run_command("wl-paste", &["-n"]),
+                "clipboard" => injector::read_clipboard(),
                 "echo" => var.params.echo.as_ref()
                     .or(var.params.format.as_ref())
                     .cloned()
                     .unwrap_or_default(),
+                "script" => var.params.source.as_deref()
+                    .map(|source| script::run_script(source, &resolved))
+                    .unwrap_or_default(),
                 _ => format!("{{{{{}}}}}", var.name),
             };
             result = result.replace(&format!("{{{{{}}}}}", var.name), &value);
+            resolved.insert(var.name.clone(), value);
         }
-        result
+        extract_cursor_marker(result)
     }
 }
 
-fn run_command(cmd: &str, args: &[&str]) -> String {
+/// A compiled regex match, checked against the buffer only after the literal
+/// trigger fast path misses.
+#[derive(Clone)]
+struct RegexMatch {
+    regex: Regex,
+    trigger: Trigger,
+}
+
+/// True if `c` counts as part of a "word" for the `word: true` boundary guard.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// True if the character immediately before a `match_len`-byte match ending at
+/// `buffer`'s end is missing (buffer starts there) or isn't a word character.
+fn word_boundary_ok(buffer: &str, match_len: usize) -> bool {
+    let boundary = buffer.len() - match_len;
+    buffer[..boundary].chars().last().map_or(true, |c| !is_word_char(c))
+}
+
+/// Numbered (`"1"`, `"2"`, ...) and named capture groups from a regex match, in the
+/// form `Trigger::expand_with` expects.
+fn captures_to_map(regex: &Regex, caps: &regex::Captures) -> HashMap<String, String> {
+    let mut map = HashMap::new();
+    for (i, name) in regex.capture_names().enumerate().skip(1) {
+        if let Some(m) = caps.get(i) {
+            map.insert(i.to_string(), m.as_str().to_string());
+            if let Some(name) = name {
+                map.insert(name.to_string(), m.as_str().to_string());
+            }
+        }
+    }
+    map
+}
+
+pub(crate) fn run_command(cmd: &str, args: &[&str]) -> String {
     process::Command::new(cmd)
         .args(args)
         .output()
@@ -128,13 +270,40 @@ fn key_to_char(key: Key, shift: bool) -> Option<char> {
     Some(if shift && c.is_ascii_alphabetic() { c.to_ascii_uppercase() } else { c })
 }
 
-fn load_yaml_recursive(dir: &PathBuf, triggers: &mut HashMap<String, Trigger>, global_vars: &mut Vec<Var>) {
+/// Subdirectory holding per-app override layers: `<config_dir>/app/<classname>/*.yaml`.
+const APP_LAYER_DIR: &str = "app";
+
+/// Literal triggers plus regex matches loaded from one directory tree. Regex
+/// matches have no natural key, so they're kept as a list and scanned only when
+/// the literal fast path in `TextExpander::process` misses.
+#[derive(Default)]
+struct Layer {
+    triggers: HashMap<String, Trigger>,
+    regexes: Vec<RegexMatch>,
+}
+
+impl Layer {
+    fn len(&self) -> usize {
+        self.triggers.len() + self.regexes.len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.triggers.is_empty() && self.regexes.is_empty()
+    }
+}
+
+fn load_yaml_recursive(dir: &PathBuf, layer: &mut Layer, global_vars: &mut Vec<Var>, is_root: bool) {
     let Ok(entries) = fs::read_dir(dir) else { return };
 
     for entry in entries.flatten() {
         let path = entry.path();
         if path.is_dir() {
-            load_yaml_recursive(&path, triggers, global_vars);
+            // The "app" dir directly under the config root is reserved for scoped
+            // per-app layers and is loaded separately by load_configs().
+            if is_root && path.file_name().and_then(|n| n.to_str()) == Some(APP_LAYER_DIR) {
+                continue;
+            }
+            load_yaml_recursive(&path, layer, global_vars, false);
         } else if path.extension().map_or(false, |e| e == "yaml" || e == "yml") {
             let Ok(content) = fs::read_to_string(&path) else { continue };
             match serde_yaml::from_str::<EspansoConfig>(&content) {
@@ -142,22 +311,35 @@ fn load_yaml_recursive(dir: &PathBuf, triggers: &mut HashMap<String, Trigger>, g
                     global_vars.extend(config.global_vars);
                     let mut count = 0;
                     for m in config.matches {
-                        let Some(replace) = m.replace else { continue };
+                        let Some(replace) = m.replace.clone() else { continue };
+                        let trigger = Trigger {
+                            replace,
+                            vars: m.vars.clone(),
+                            scope: m.scope.clone(),
+                            word: m.word,
+                        };
 
                         // Collect all triggers: singular `trigger` and plural `triggers`
                         let mut all_triggers = Vec::new();
-                        if let Some(t) = m.trigger {
-                            all_triggers.push(t);
+                        if let Some(t) = &m.trigger {
+                            all_triggers.push(t.clone());
                         }
-                        all_triggers.extend(m.triggers);
+                        all_triggers.extend(m.triggers.clone());
 
                         for trig in all_triggers {
-                            triggers.insert(trig, Trigger {
-                                replace: replace.clone(),
-                                vars: m.vars.clone(),
-                            });
+                            layer.triggers.insert(trig, trigger.clone());
                             count += 1;
                         }
+
+                        if let Some(pattern) = &m.regex {
+                            match Regex::new(&format!("{}$", pattern)) {
+                                Ok(regex) => {
+                                    layer.regexes.push(RegexMatch { regex, trigger: trigger.clone() });
+                                    count += 1;
+                                }
+                                Err(e) => eprintln!("Warning: invalid regex {:?} in {:?}: {}", pattern, path, e),
+                            }
+                        }
                     }
                     if count > 0 {
                         eprintln!("Loaded {} triggers from {:?}", count, path);
@@ -171,27 +353,67 @@ fn load_yaml_recursive(dir: &PathBuf, triggers: &mut HashMap<String, Trigger>, g
     }
 }
 
-fn load_configs() -> HashMap<String, Trigger> {
-    let mut triggers = HashMap::new();
+/// Prepend `global_vars` to every match's own vars (so they're available for expansion).
+fn merge_global_vars(layer: &mut Layer, global_vars: &[Var]) {
+    if global_vars.is_empty() { return }
+    for trigger in layer.triggers.values_mut() {
+        let mut merged = global_vars.to_vec();
+        merged.extend(trigger.vars.clone());
+        trigger.vars = merged;
+    }
+    for rm in &mut layer.regexes {
+        let mut merged = global_vars.to_vec();
+        merged.extend(rm.trigger.vars.clone());
+        rm.trigger.vars = merged;
+    }
+}
+
+fn load_one_layer(dir: &PathBuf, is_root: bool) -> Layer {
+    let mut layer = Layer::default();
     let mut global_vars = Vec::new();
-    let config_dir = get_config_path();
+    load_yaml_recursive(dir, &mut layer, &mut global_vars, is_root);
+    merge_global_vars(&mut layer, &global_vars);
+    layer
+}
 
-    if config_dir.exists() {
-        load_yaml_recursive(&config_dir, &mut triggers, &mut global_vars);
-    } else {
+/// A base layer plus optional per-app override layers, keyed by app class. Scoped
+/// layers win over the base on matching trigger names (last-write-wins), selected
+/// at match time by `TextExpander` based on the focused window.
+struct ConfigLayers {
+    base: Layer,
+    scoped: HashMap<String, Layer>,
+}
+
+impl ConfigLayers {
+    fn is_empty(&self) -> bool {
+        self.base.is_empty() && self.scoped.values().all(Layer::is_empty)
+    }
+
+    fn trigger_count(&self) -> usize {
+        self.base.len() + self.scoped.values().map(Layer::len).sum::<usize>()
+    }
+}
+
+fn load_configs(config_dir: &PathBuf) -> ConfigLayers {
+    if !config_dir.exists() {
         eprintln!("Config directory not found: {:?}", config_dir);
+        return ConfigLayers { base: Layer::default(), scoped: HashMap::new() };
     }
 
-    // Prepend global_vars to each trigger's vars (so they're available for expansion)
-    if !global_vars.is_empty() {
-        for trigger in triggers.values_mut() {
-            let mut merged = global_vars.clone();
-            merged.extend(trigger.vars.clone());
-            trigger.vars = merged;
+    let base = load_one_layer(config_dir, true);
+
+    let mut scoped = HashMap::new();
+    let app_dir = config_dir.join(APP_LAYER_DIR);
+    if let Ok(entries) = fs::read_dir(&app_dir) {
+        for entry in entries.flatten() {
+            let class_dir = entry.path();
+            if !class_dir.is_dir() { continue }
+            let Some(class_name) = class_dir.file_name().and_then(|n| n.to_str()) else { continue };
+            scoped.insert(class_name.to_string(), load_one_layer(&class_dir, false));
         }
     }
 
-    triggers
+    ConfigLayers { base, scoped }
 }
 
 fn get_config_path() -> PathBuf {
@@ -246,66 +468,174 @@ fn find_keyboards() -> Vec<Device> {
     }
 }
 
-fn get_wayland_env() -> Vec<(String, String)> {
-    let mut env_vars = Vec::new();
-    let real_uid = env::var("SUDO_UID").unwrap_or_default();
+/// Print every `/dev/input/eventN` that looks like a keyboard, without picking one.
+fn list_devices() {
+    let Ok(entries) = fs::read_dir("/dev/input") else {
+        eprintln!("Cannot read /dev/input");
+        return;
+    };
 
-    if let Ok(xdg) = env::var("XDG_RUNTIME_DIR") {
-        env_vars.push(("XDG_RUNTIME_DIR".into(), xdg));
-    } else if !real_uid.is_empty() {
-        env_vars.push(("XDG_RUNTIME_DIR".into(), format!("/run/user/{}", real_uid)));
-    }
+    let mut found = false;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.to_string_lossy().contains("event") { continue }
+
+        let Ok(device) = Device::open(&path) else { continue };
+        if !device.supported_events().contains(EventType::KEY) { continue }
+
+        let Some(keys) = device.supported_keys() else { continue };
+        if !keys.contains(Key::KEY_A) || !keys.contains(Key::KEY_Z) { continue }
 
-    env_vars.push(("WAYLAND_DISPLAY".into(),
-        env::var("WAYLAND_DISPLAY").unwrap_or_else(|_| "wayland-1".into())));
+        found = true;
+        println!("{:?}\t{}", path, device.name().unwrap_or("unknown"));
+    }
 
-    if let Ok(user) = env::var("SUDO_USER") {
-        env_vars.push(("USER".into(), user));
+    if !found {
+        eprintln!("No keyboard-like devices found under /dev/input");
     }
-    env_vars
 }
 
-fn run_wtype(args: &[&str]) {
-    if let Ok(sudo_user) = env::var("SUDO_USER") {
-        let mut cmd = process::Command::new("sudo");
-        cmd.arg("-u").arg(&sudo_user).arg("env");
-        for (k, v) in get_wayland_env() {
-            cmd.arg(format!("{}={}", k, v));
+/// Open a single, explicitly pinned device instead of running the find_keyboards() heuristic.
+fn open_device(path: &PathBuf) -> Vec<Device> {
+    match Device::open(path) {
+        Ok(device) => vec![device],
+        Err(e) => {
+            eprintln!("Failed to open {:?}: {}", path, e);
+            Vec::new()
         }
-        cmd.arg("wtype").args(args);
-        let _ = cmd.status();
-    } else {
-        let _ = process::Command::new("wtype").args(args).status();
     }
 }
 
-fn type_expansion(backspaces: usize, text: &str) {
-    let mut args: Vec<String> = Vec::new();
-    for _ in 0..backspaces {
-        args.push("-k".into());
-        args.push("BackSpace".into());
-    }
-    args.push("--".into());
-    args.push(text.into());
+/// How long a detected app class is trusted before `active_app_class()` is
+/// re-run -- re-probing on every keystroke would mean a process spawn per key.
+const ACTIVE_CLASS_REFRESH: Duration = Duration::from_millis(250);
+
+/// Regex matches have no statically known length, so a config with any regex
+/// match gets at least this much rolling buffer -- generous enough for the
+/// multi-word patterns regex triggers are meant for, regardless of how short
+/// the longest literal trigger is.
+const REGEX_BUFFER_FLOOR: usize = 256;
 
-    let refs: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
-    run_wtype(&refs);
+fn layers_max_len(layers: &ConfigLayers) -> usize {
+    let literal_max = layers.base.triggers.keys()
+        .chain(layers.scoped.values().flat_map(|l| l.triggers.keys()))
+        .map(|k| k.len())
+        .max()
+        .unwrap_or(64);
+
+    let has_regexes = !layers.base.regexes.is_empty()
+        || layers.scoped.values().any(|l| !l.regexes.is_empty());
+
+    if has_regexes { literal_max.max(REGEX_BUFFER_FLOOR) } else { literal_max }
 }
 
-struct TextExpander {
+/// Whether any match in `layers` actually depends on the focused window's app
+/// class, so `TextExpander` knows whether probing for it is worth the cost.
+fn needs_active_class_probe(layers: &ConfigLayers) -> bool {
+    !layers.scoped.is_empty()
+        || layers.base.triggers.values().any(|t| t.scope.is_scoped())
+        || layers.base.regexes.iter().any(|rm| rm.trigger.scope.is_scoped())
+}
+
+/// The base layer with the current app class's override layer (if any) merged on
+/// top: literal triggers last-write-wins, scoped regexes checked before base ones.
+///
+/// Owned rather than borrowed: it's rebuilt only on class change or reload (see
+/// `rebuild_effective_layer`), not on every `process()` call, so the merge cost
+/// doesn't land on the per-keystroke fast path.
+#[derive(Default)]
+struct EffectiveLayer {
     triggers: HashMap<String, Trigger>,
+    regexes: Vec<RegexMatch>,
+}
+
+struct TextExpander {
+    base: Layer,
+    scoped: HashMap<String, Layer>,
     buffer: String,
     max_len: usize,
     shift: bool,
+    active_class: Option<String>,
+    active_class_checked_at: Option<Instant>,
+    effective: EffectiveLayer,
+    /// Whether any loaded match actually depends on the app class, so the
+    /// (sudo + subprocess) probe in `refresh_active_class` can be skipped
+    /// entirely for configs that never use app scoping.
+    probe_active_class: bool,
 }
 
 impl TextExpander {
-    fn new(triggers: HashMap<String, Trigger>) -> Self {
-        let max_len = triggers.keys().map(|k| k.len()).max().unwrap_or(64);
-        Self { triggers, buffer: String::with_capacity(max_len + 1), max_len, shift: false }
+    fn new(layers: ConfigLayers) -> Self {
+        let max_len = layers_max_len(&layers);
+        let probe_active_class = needs_active_class_probe(&layers);
+        let mut expander = Self {
+            base: layers.base,
+            scoped: layers.scoped,
+            buffer: String::with_capacity(max_len + 1),
+            max_len,
+            shift: false,
+            active_class: None,
+            active_class_checked_at: None,
+            effective: EffectiveLayer::default(),
+            probe_active_class,
+        };
+        expander.rebuild_effective_layer();
+        expander
+    }
+
+    /// Swap in a freshly loaded config. The buffer is cleared so a partial match
+    /// spanning the reload can't fire against triggers that no longer exist.
+    fn reload(&mut self, layers: ConfigLayers) {
+        self.max_len = layers_max_len(&layers);
+        self.probe_active_class = needs_active_class_probe(&layers);
+        self.base = layers.base;
+        self.scoped = layers.scoped;
+        self.buffer.clear();
+        self.rebuild_effective_layer();
+    }
+
+    /// Re-detect the focused window's app class, throttled by `ACTIVE_CLASS_REFRESH`,
+    /// and rebuild the effective layer only if the class actually changed. No-op
+    /// when nothing in the config is app-scoped, so typing with a plain config
+    /// never shells out to `xdotool` at all.
+    fn refresh_active_class(&mut self) {
+        if !self.probe_active_class {
+            return;
+        }
+
+        let stale = self.active_class_checked_at
+            .map_or(true, |at| at.elapsed() >= ACTIVE_CLASS_REFRESH);
+        if stale {
+            let new_class = winclass::active_app_class();
+            if new_class != self.active_class {
+                self.active_class = new_class;
+                self.rebuild_effective_layer();
+            }
+            self.active_class_checked_at = Some(Instant::now());
+        }
+    }
+
+    /// Merge the base layer with `self.active_class`'s override layer (if any)
+    /// into `self.effective`. Called on class change and reload only -- see
+    /// `EffectiveLayer`'s doc comment for why that matters.
+    fn rebuild_effective_layer(&mut self) {
+        let mut triggers = self.base.triggers.clone();
+        let mut regexes = self.base.regexes.clone();
+
+        if let Some(layer) = self.active_class.as_deref().and_then(|c| self.scoped.get(c)) {
+            for (k, v) in &layer.triggers {
+                triggers.insert(k.clone(), v.clone());
+            }
+            // Scoped regexes are checked before base ones so they can override them.
+            let mut scoped_first = layer.regexes.clone();
+            scoped_first.extend(regexes);
+            regexes = scoped_first;
+        }
+
+        self.effective = EffectiveLayer { triggers, regexes };
     }
 
-    fn process(&mut self, key: Key, pressed: bool) -> Option<(usize, String)> {
+    fn process(&mut self, key: Key, pressed: bool) -> Option<(usize, String, usize)> {
         if key == Key::KEY_LEFTSHIFT || key == Key::KEY_RIGHTSHIFT {
             self.shift = pressed;
             return None;
@@ -325,11 +655,33 @@ impl TextExpander {
                 self.buffer.drain(..self.buffer.len() - self.max_len);
             }
 
-            for (trig, data) in &self.triggers {
-                if self.buffer.ends_with(trig) {
-                    let result = (trig.len(), data.expand());
+            self.refresh_active_class();
+            let class = self.active_class.as_deref();
+
+            for (trig, data) in &self.effective.triggers {
+                if !data.scope.allows(class) { continue }
+                if !self.buffer.ends_with(trig.as_str()) { continue }
+                if data.word && !word_boundary_ok(&self.buffer, trig.len()) { continue }
+
+                let (text, cursor) = data.expand();
+                self.buffer.clear();
+                return Some((trig.len(), text, cursor));
+            }
+
+            if !self.effective.regexes.is_empty() {
+                for rm in &self.effective.regexes {
+                    if !rm.trigger.scope.allows(class) { continue }
+                    let Some(caps) = rm.regex.captures(&self.buffer) else { continue };
+                    let whole = caps.get(0).unwrap();
+                    if rm.trigger.word && !word_boundary_ok(&self.buffer, whole.len()) { continue }
+
+                    let capture_vars = captures_to_map(&rm.regex, &caps);
+                    let (text, cursor) = rm.trigger.expand_with(&capture_vars);
+                    // Backspace count is in characters, not bytes -- a regex match over
+                    // non-ASCII text (e.g. "café") would otherwise over-delete.
+                    let consumed = whole.as_str().chars().count();
                     self.buffer.clear();
-                    return Some(result);
+                    return Some((consumed, text, cursor));
                 }
             }
         }
@@ -337,6 +689,30 @@ impl TextExpander {
     }
 }
 
+/// Print one trigger/regex's resolved expansion for `--dump`.
+fn print_expansion(label: &str, (text, cursor): (String, usize)) {
+    if cursor > 0 {
+        println!("{} -> {} (cursor {} back)", label, text, cursor);
+    } else {
+        println!("{} -> {}", label, text);
+    }
+}
+
+/// Print a `--dump` section for one config layer, sorted by trigger name.
+fn dump_layer(label: &str, layer: &Layer) {
+    if layer.is_empty() { return }
+    println!("# {}", label);
+
+    let mut names: Vec<&String> = layer.triggers.keys().collect();
+    names.sort();
+    for name in names {
+        print_expansion(name, layer.triggers[name].expand());
+    }
+    for rm in &layer.regexes {
+        print_expansion(rm.regex.as_str(), rm.trigger.expand());
+    }
+}
+
 fn daemonize() {
     // Fork and exit parent
     match unsafe { libc::fork() } {
@@ -363,44 +739,95 @@ fn daemonize() {
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-    let daemon_mode = args.iter().any(|a| a == "-d" || a == "--daemon");
+    let cli = Cli::parse();
 
-    eprintln!("text_expander - lightweight espanso replacement for Wayland");
+    if cli.list_devices {
+        list_devices();
+        return;
+    }
+
+    eprintln!("text_expander - lightweight espanso replacement for Wayland and X11");
 
-    let triggers = load_configs();
-    if triggers.is_empty() {
-        eprintln!("No triggers loaded. Create config in ~/.config/text_expander/");
+    let config_dir = cli.config.clone().unwrap_or_else(get_config_path);
+    let layers = load_configs(&config_dir);
+    if layers.is_empty() {
+        eprintln!("No triggers loaded. Create config in {:?}", config_dir);
         process::exit(1);
     }
-    eprintln!("Loaded {} triggers", triggers.len());
+    eprintln!("Loaded {} triggers", layers.trigger_count());
+
+    if cli.dump {
+        dump_layer("base", &layers.base);
+        let mut classes: Vec<&String> = layers.scoped.keys().collect();
+        classes.sort();
+        for class in classes {
+            dump_layer(&format!("app/{}", class), &layers.scoped[class]);
+        }
+        return;
+    }
 
-    let mut keyboards = find_keyboards();
+    let mut keyboards = match &cli.device {
+        Some(path) => open_device(path),
+        None => find_keyboards(),
+    };
     if keyboards.is_empty() {
         eprintln!("No keyboards found. Need read access to /dev/input/*");
         process::exit(1);
     }
 
-    if daemon_mode {
+    if cli.daemon {
         eprintln!("Daemonizing...");
         daemonize();
     } else {
         eprintln!("Ready! (use -d/--daemon to run in background)");
     }
 
-    let mut expander = TextExpander::new(triggers);
+    let backend = match cli.backend {
+        Some(name) => injector::backend_by_name(name),
+        None => match injector::detect_backend() {
+            Some(backend) => backend,
+            None => {
+                eprintln!("No injection backend found. Install wtype, ydotool, or xdotool.");
+                process::exit(1);
+            }
+        },
+    };
+
+    let mut expander = TextExpander::new(layers);
     let raw_fds: Vec<i32> = keyboards.iter().map(|k| k.as_raw_fd()).collect();
+    let reload_watcher = ReloadWatcher::install(&config_dir);
 
     loop {
         let mut pollfds: Vec<libc::pollfd> = raw_fds.iter()
             .map(|&fd| libc::pollfd { fd, events: libc::POLLIN, revents: 0 })
             .collect();
+        let pipe_idx = pollfds.len();
+        pollfds.push(libc::pollfd { fd: reload_watcher.pipe_read, events: libc::POLLIN, revents: 0 });
+        let inotify_idx = reload_watcher.inotify_fd.map(|fd| {
+            let idx = pollfds.len();
+            pollfds.push(libc::pollfd { fd, events: libc::POLLIN, revents: 0 });
+            idx
+        });
 
         if unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as _, -1) } < 0 {
             continue;
         }
 
-        let ready: Vec<usize> = pollfds.iter().enumerate()
+        let pipe_ready = pollfds[pipe_idx].revents & libc::POLLIN != 0;
+        let inotify_ready = inotify_idx.map_or(false, |i| pollfds[i].revents & libc::POLLIN != 0);
+        if pipe_ready || inotify_ready {
+            reload_watcher.drain(pipe_ready, inotify_ready);
+            eprintln!("Reloading configs from {:?}", config_dir);
+            let layers = load_configs(&config_dir);
+            if layers.is_empty() {
+                eprintln!("Reload produced no triggers, keeping the previous table");
+            } else {
+                eprintln!("Loaded {} triggers", layers.trigger_count());
+                expander.reload(layers);
+            }
+        }
+
+        let ready: Vec<usize> = pollfds.iter().take(raw_fds.len()).enumerate()
             .filter(|(_, p)| p.revents & libc::POLLIN != 0)
             .map(|(i, _)| i).collect();
 
@@ -410,9 +837,13 @@ fn main() {
             if let Ok(events) = keyboards[i].fetch_events() {
                 for ev in events {
                     if ev.event_type() == EventType::KEY {
-                        if let Some((n, text)) = expander.process(Key::new(ev.code()), ev.value() == 1) {
+                        if let Some((n, text, cursor)) = expander.process(Key::new(ev.code()), ev.value() == 1) {
                             thread::sleep(Duration::from_millis(10));
-                            type_expansion(n, &text);
+                            backend.backspaces(n);
+                            backend.type_text(&text);
+                            if cursor > 0 {
+                                backend.cursor_left(cursor);
+                            }
                             expanded = true;
                         }
                     }
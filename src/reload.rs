@@ -0,0 +1,82 @@
+//! Self-pipe plumbing so SIGHUP/SIGUSR1 (and config dir writes) can wake the
+//! `libc::poll(-1)` loop in `main` immediately, instead of waiting for the next keystroke.
+
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+static SELF_PIPE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+// Signal-safe: only writes one byte to an fd, no allocation, no locking.
+extern "C" fn handle_reload_signal(_sig: libc::c_int) {
+    let fd = SELF_PIPE_WRITE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let byte: u8 = 1;
+        unsafe { libc::write(fd, &byte as *const u8 as *const libc::c_void, 1) };
+    }
+}
+
+/// Wires SIGHUP/SIGUSR1 through a self-pipe, and (best-effort) watches the config
+/// directory with inotify, so edits to files directly in it take effect without a
+/// manual signal. The watch is not recursive: edits inside subdirectories (including
+/// `app/<classname>/`) are picked up only on a signal or a later top-level change.
+pub struct ReloadWatcher {
+    pub pipe_read: RawFd,
+    pub inotify_fd: Option<RawFd>,
+}
+
+impl ReloadWatcher {
+    pub fn install(config_dir: &Path) -> Self {
+        let mut fds = [0; 2];
+        // O_NONBLOCK on both ends: the signal handler's write() must never block, and
+        // drain()'s read-to-EOF loop would hang forever on a blocking empty pipe.
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK) } != 0 {
+            eprintln!("Failed to create self-pipe for reload signals");
+        }
+        let (read_fd, write_fd) = (fds[0], fds[1]);
+        SELF_PIPE_WRITE.store(write_fd, Ordering::Relaxed);
+
+        unsafe {
+            libc::signal(libc::SIGHUP, handle_reload_signal as libc::sighandler_t);
+            libc::signal(libc::SIGUSR1, handle_reload_signal as libc::sighandler_t);
+        }
+
+        let inotify_fd = Self::watch_config_dir(config_dir);
+
+        Self { pipe_read: read_fd, inotify_fd }
+    }
+
+    fn watch_config_dir(config_dir: &Path) -> Option<RawFd> {
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK) };
+        if fd < 0 {
+            eprintln!("inotify_init1 failed, config dir changes won't trigger an automatic reload");
+            return None;
+        }
+
+        let path = CString::new(config_dir.as_os_str().as_bytes()).ok()?;
+        let mask = libc::IN_CLOSE_WRITE | libc::IN_CREATE | libc::IN_MOVED_TO;
+        if unsafe { libc::inotify_add_watch(fd, path.as_ptr(), mask) } < 0 {
+            eprintln!("Failed to watch {:?} for changes, reload on write disabled", config_dir);
+            unsafe { libc::close(fd) };
+            return None;
+        }
+        Some(fd)
+    }
+
+    /// Drain whichever fds woke `poll`. The bytes themselves carry no information --
+    /// either fd firing just means "something changed, reload" -- so we discard them.
+    pub fn drain(&self, pipe_ready: bool, inotify_ready: bool) {
+        if pipe_ready {
+            let mut buf = [0u8; 64];
+            while unsafe { libc::read(self.pipe_read, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+        }
+        if inotify_ready {
+            if let Some(fd) = self.inotify_fd {
+                let mut buf = [0u8; 4096];
+                while unsafe { libc::read(fd, buf.as_mut_ptr() as *mut _, buf.len()) } > 0 {}
+            }
+        }
+    }
+}
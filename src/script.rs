@@ -0,0 +1,62 @@
+//! Embedded Lua `script` variable type. Each expansion gets a fresh `mlua::Lua`
+//! state with a small host API (`clipboard()`, `date(fmt)`, `env(name)`, and a
+//! `vars` table of already-resolved vars), so triggers can branch and manipulate
+//! strings without forking a shell on every keystroke.
+
+use mlua::{Lua, Value};
+use std::collections::HashMap;
+
+use crate::injector;
+use crate::run_command;
+
+/// Evaluate `source` as Lua and coerce its return value to a string. Any error --
+/// syntax, runtime, or a nil/unconvertible return -- logs to stderr and yields an
+/// empty string, so a broken script never blocks typing.
+pub fn run_script(source: &str, resolved: &HashMap<String, String>) -> String {
+    let lua = Lua::new();
+
+    if let Err(e) = install_host_api(&lua, resolved) {
+        eprintln!("Lua script setup failed: {}", e);
+        return String::new();
+    }
+
+    match lua.load(source).eval::<Value>() {
+        Ok(value) => lua.coerce_string(value)
+            .ok()
+            .flatten()
+            .and_then(|s| s.to_str().map(|s| s.to_string()).ok())
+            .unwrap_or_else(|| {
+                eprintln!("Lua script returned nil or a value that can't be converted to a string");
+                String::new()
+            }),
+        Err(e) => {
+            eprintln!("Lua script error: {}", e);
+            String::new()
+        }
+    }
+}
+
+fn install_host_api(lua: &Lua, resolved: &HashMap<String, String>) -> mlua::Result<()> {
+    let globals = lua.globals();
+
+    globals.set("clipboard", lua.create_function(|_, ()| {
+        Ok(injector::read_clipboard())
+    })?)?;
+
+    globals.set("date", lua.create_function(|_, fmt: Option<String>| {
+        let fmt = fmt.unwrap_or_else(|| "%Y-%m-%d".to_string());
+        Ok(run_command("date", &[&format!("+{}", fmt)]))
+    })?)?;
+
+    globals.set("env", lua.create_function(|_, name: String| {
+        Ok(std::env::var(name).unwrap_or_default())
+    })?)?;
+
+    let vars = lua.create_table()?;
+    for (name, value) in resolved {
+        vars.set(name.as_str(), value.as_str())?;
+    }
+    globals.set("vars", vars)?;
+
+    Ok(())
+}
@@ -0,0 +1,19 @@
+//! Detects the focused window's app class, so `TextExpander` can select a
+//! per-app config layer and evaluate match `filter`/`exclude` lists.
+
+use crate::injector;
+
+/// Best-effort lookup of the focused window's class/app-id via `xdotool
+/// getactivewindow getwindowclassname`, which needs an X server. No Wayland
+/// compositor is queried: on Wayland this only sees XWayland windows, and returns
+/// `None` whenever `xdotool` prints nothing.
+///
+/// Routed through `injector::run_as_user_output` rather than run directly: the
+/// daemon typically runs as root (for /dev/input access), where `DISPLAY` isn't
+/// in its own environment, so the probe has to reach the user's session the same
+/// way injection does. Degrades to `None` (base layer only) when no session can
+/// be reached.
+pub fn active_app_class() -> Option<String> {
+    let class = injector::run_as_user_output("xdotool", &["getactivewindow", "getwindowclassname"]);
+    if class.is_empty() { None } else { Some(class) }
+}